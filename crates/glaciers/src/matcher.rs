@@ -1,8 +1,10 @@
 //! Matching module for associating logs and traces with ABI item signatures.
-//! 
+//!
 //! This module provides functionality to match Ethereum logs and traces with their corresponding
 //! ABI signatures using various matching strategies. It supports matching by topic0/4bytes signatures
-//! with and without address verification.
+//! with and without address verification, and, when a topic0/selector collides across several
+//! distinct signatures, either the most-frequent-signature heuristic or trial-decode
+//! disambiguation (see [`MatchMode`]).
 
 use polars::prelude::*;
 use thiserror::Error;
@@ -16,6 +18,88 @@ pub enum MatcherError {
     PolarsError(#[from] PolarsError),
 }
 
+/// Controls how a topic0/4-byte-selector collision between several distinct `full_signature`
+/// candidates is resolved by `match_logs_by_topic0` and `match_traces_by_4bytes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchMode {
+    /// Resolve a collision by picking the signature that appears most often for the
+    /// hash/selector in `abi_df`. Fast, but can silently mislabel a row.
+    #[default]
+    Frequency,
+    /// Resolve a collision by attempting to ABI-decode the row's payload (a log's `data`, or a
+    /// trace's calldata past the 4-byte selector) against each candidate's parameter types, and
+    /// keeping the candidate whose head/tail layout consumes the payload with no short or
+    /// trailing bytes. Ties are broken by the existing frequency count; if no candidate decodes
+    /// cleanly, the row is left unmatched.
+    Decode,
+}
+
+/// Scopes a log match to a subset of addresses, topic0 hashes, and an inclusive block-number
+/// range, applied as a predicate before the join -- analogous to an `eth_getLogs` filter. Every
+/// field defaults to `None`, meaning "match all".
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Restrict to logs emitted by one of these contract addresses.
+    pub address: Option<Vec<String>>,
+    /// Restrict to logs whose topic0 is one of these event signature hashes.
+    pub topic0: Option<Vec<String>>,
+    /// Restrict to logs within this inclusive `[start, end]` block-number range.
+    pub block_number_range: Option<(i64, i64)>,
+}
+
+impl LogFilter {
+    fn apply(&self, log_df: LazyFrame) -> LazyFrame {
+        let address_alias = get_config().log_decoder.log_schema.log_alias.address;
+        let topic0_alias = get_config().log_decoder.log_schema.log_alias.topic0;
+        let block_number_alias = get_config().log_decoder.log_schema.log_alias.block_number;
+
+        let mut log_df = log_df;
+        if let Some(addresses) = &self.address {
+            log_df = log_df.filter(col(address_alias.as_str()).is_in(lit(Series::new("", addresses))));
+        }
+        if let Some(topic0s) = &self.topic0 {
+            log_df = log_df.filter(col(topic0_alias.as_str()).is_in(lit(Series::new("", topic0s))));
+        }
+        if let Some((start, end)) = self.block_number_range {
+            log_df = log_df.filter(col(block_number_alias.as_str()).gt_eq(lit(start)).and(col(block_number_alias.as_str()).lt_eq(lit(end))));
+        }
+        log_df
+    }
+}
+
+/// Scopes a trace match to a subset of addresses, 4-byte selectors, and an inclusive
+/// block-number range, applied as a predicate before the join. Every field defaults to `None`,
+/// meaning "match all".
+#[derive(Debug, Clone, Default)]
+pub struct TraceFilter {
+    /// Restrict to traces whose call target (`action.to`) is one of these addresses.
+    pub address: Option<Vec<String>>,
+    /// Restrict to traces whose 4-byte selector is one of these.
+    pub selector: Option<Vec<String>>,
+    /// Restrict to traces within this inclusive `[start, end]` block-number range.
+    pub block_number_range: Option<(i64, i64)>,
+}
+
+impl TraceFilter {
+    fn apply(&self, trace_df: LazyFrame) -> LazyFrame {
+        let action_to = get_config().trace_decoder.trace_schema.trace_alias.action_to;
+        let selector_alias = get_config().trace_decoder.trace_schema.trace_alias.selector;
+        let block_number_alias = get_config().trace_decoder.trace_schema.trace_alias.block_number;
+
+        let mut trace_df = trace_df;
+        if let Some(addresses) = &self.address {
+            trace_df = trace_df.filter(col(action_to.as_str()).is_in(lit(Series::new("", addresses))));
+        }
+        if let Some(selectors) = &self.selector {
+            trace_df = trace_df.filter(col(selector_alias.as_str()).is_in(lit(Series::new("", selectors))));
+        }
+        if let Some((start, end)) = self.block_number_range {
+            trace_df = trace_df.filter(col(block_number_alias.as_str()).gt_eq(lit(start)).and(col(block_number_alias.as_str()).lt_eq(lit(end))));
+        }
+        trace_df
+    }
+}
+
 /// Matches logs with ABI signatures using both topic0 and contract address.
 ///
 /// This function performs a left join between logs and ABI signatures, matching on:
@@ -30,12 +114,17 @@ pub enum MatcherError {
 /// # Returns
 /// * `Result<DataFrame>` - Joined DataFrame with matched signatures, or error
 pub fn match_logs_by_topic0_address(log_df: DataFrame, abi_df: DataFrame) -> Result<DataFrame, MatcherError> {
+    Ok(match_logs_by_topic0_address_lazy(log_df.lazy(), abi_df).collect()?)
+}
+
+/// Lazy core of `match_logs_by_topic0_address`, kept separate so a caller-supplied predicate
+/// (e.g. `LogFilter`) can be fused into the same lazy pipeline as the join instead of being
+/// collected beforehand.
+fn match_logs_by_topic0_address_lazy(log_df: LazyFrame, abi_df: DataFrame) -> LazyFrame {
     let topic0_alias = get_config().log_decoder.log_schema.log_alias.topic0;
     let address_alias = get_config().log_decoder.log_schema.log_alias.address;
 
-
-    let logs_left_join_abi_df = log_df
-        .lazy()
+    log_df
         .with_column((lit(1 as u32) +
             col("topic1").is_not_null() +
             col("topic2").is_not_null() +
@@ -46,15 +135,36 @@ pub fn match_logs_by_topic0_address(log_df: DataFrame, abi_df: DataFrame) -> Res
             [col("hash"), col("address"), col("num_indexed_args")],
             JoinArgs::new(JoinType::Left),
         )
-        .collect()?;
+}
 
-    Ok(logs_left_join_abi_df)
+/// Same as `match_logs_by_topic0_address`, but first scopes `log_df` to `filter`, so the join
+/// only runs over the contracts/events/block range the caller cares about.
+///
+/// # Arguments
+/// * `log_df` - DataFrame containing log entries
+/// * `abi_df` - DataFrame containing ABI signatures
+/// * `filter` - predicate applied to `log_df` before matching
+///
+/// # Returns
+/// * `Result<DataFrame>` - Joined DataFrame with matched signatures, or error
+pub fn match_logs_by_topic0_address_with_filter(log_df: DataFrame, abi_df: DataFrame, filter: &LogFilter) -> Result<DataFrame, MatcherError> {
+    Ok(match_logs_by_topic0_address_lazy(filter.apply(log_df.lazy()), abi_df).collect()?)
 }
 
-/// Matches logs with ABI signatures using a two-step matching process.
+/// Matches logs with ABI signatures using a three-step matching process.
+///
+/// First attempts to match logs using both topic0 and address. For logs still unmatched, tries
+/// matching only by topic0, resolving any collision between candidate signatures according to
+/// `mode`. Anonymous events never emit their signature hash in topic0 -- topic0 is itself an
+/// indexed argument for them -- so logs still unmatched after those two steps are given a final
+/// chance to match against the `anonymous == true` subset of `abi_df`, keyed on the number of
+/// present topics instead of on topic0: first scoped to the log's address, and then, as a last
+/// resort, by the topic count alone using the most frequent signature.
 ///
-/// First attempts to match logs using both topic0 and address. For unmatched logs,
-/// tries matching only by topic0 using the most frequent signature in the database for each hash.
+/// A `match_source` column is added to the result: `"address"` for logs matched in the first or
+/// anonymous-address step, `"decoded"`/`"frequency"` for logs matched in the topic0 step
+/// (depending on `mode` and whether a collision was resolved by decoding), and `"frequency"` for
+/// the final anonymous fallback.
 ///
 /// # Arguments
 /// * `log_df` - DataFrame containing log entries
@@ -63,46 +173,297 @@ pub fn match_logs_by_topic0_address(log_df: DataFrame, abi_df: DataFrame) -> Res
 /// # Returns
 /// * `Result<DataFrame>` - DataFrame with matched signatures, or error
 pub fn match_logs_by_topic0(log_df: DataFrame, abi_df: DataFrame) -> Result<DataFrame, MatcherError> {
-    let logs_1 = match_logs_by_topic0_address(log_df.clone(), abi_df.clone())?;
+    match_logs_by_topic0_with_mode(log_df, abi_df, MatchMode::default())
+}
+
+/// Same as `match_logs_by_topic0`, but resolves a topic0 collision between several candidate
+/// signatures according to `mode` instead of always picking the most frequent one.
+///
+/// # Arguments
+/// * `log_df` - DataFrame containing log entries
+/// * `abi_df` - DataFrame containing ABI signatures
+/// * `mode` - How to resolve a topic0 collision between several candidate signatures
+///
+/// # Returns
+/// * `Result<DataFrame>` - DataFrame with matched signatures, or error
+pub fn match_logs_by_topic0_with_mode(log_df: DataFrame, abi_df: DataFrame, mode: MatchMode) -> Result<DataFrame, MatcherError> {
     let log_df_cols: Vec<Expr> = log_df.get_columns().iter().map(|s| col(s.name())).collect();
+    let logs_1 = match_logs_by_topic0_address(log_df, abi_df.clone())?;
+    match_logs_by_topic0_continue(logs_1, log_df_cols, abi_df, mode)
+}
+
+/// Same as `match_logs_by_topic0_with_mode`, but first scopes `log_df` to `filter`, so the join
+/// only runs over the contracts/events/block range the caller cares about, with the predicate
+/// fused into the same lazy pipeline as the address-matching join.
+///
+/// # Arguments
+/// * `log_df` - DataFrame containing log entries
+/// * `abi_df` - DataFrame containing ABI signatures
+/// * `mode` - How to resolve a topic0 collision between several candidate signatures
+/// * `filter` - predicate applied to `log_df` before matching
+///
+/// # Returns
+/// * `Result<DataFrame>` - DataFrame with matched signatures, or error
+pub fn match_logs_by_topic0_with_filter(log_df: DataFrame, abi_df: DataFrame, mode: MatchMode, filter: &LogFilter) -> Result<DataFrame, MatcherError> {
+    let log_df_cols: Vec<Expr> = log_df.get_columns().iter().map(|s| col(s.name())).collect();
+    let logs_1 = match_logs_by_topic0_address_lazy(filter.apply(log_df.lazy()), abi_df.clone()).collect()?;
+    match_logs_by_topic0_continue(logs_1, log_df_cols, abi_df, mode)
+}
+
+/// Shared tail of `match_logs_by_topic0`/`match_logs_by_topic0_with_filter`: resolves the topic0
+/// and anonymous-event collisions against the already-computed address-matching step `logs_1`.
+fn match_logs_by_topic0_continue(logs_1: DataFrame, log_df_cols: Vec<Expr>, abi_df: DataFrame, mode: MatchMode) -> Result<DataFrame, MatcherError> {
     // Split the logs into matched and not matched in the first step
-    let logs_address_matched = logs_1.clone().lazy().filter(col("full_signature").is_not_null()).collect()?;
-    let logs_address_not_matched = logs_1.lazy().filter(col("full_signature").is_null()).select(log_df_cols);
+    let logs_address_matched = logs_1.clone().lazy().filter(col("full_signature").is_not_null())
+        .with_column(lit("address").alias("match_source"))
+        .collect()?;
+    let logs_address_not_matched = logs_1.lazy().filter(col("full_signature").is_null()).select(log_df_cols.clone());
 
-    // create an abi_df with the most frequent signature for each hash
-    let abi_df = abi_df
-        .lazy()
-        //count the number of rows for each full_signature
-        .group_by(["hash", "full_signature", "name", "anonymous", "num_indexed_args"])
+    let topic0_alias = get_config().log_decoder.log_schema.log_alias.topic0;
+    let data_alias = get_config().log_decoder.log_schema.log_alias.data;
+    let logs_2 = logs_address_not_matched
+        .with_column((lit(1 as u32) +
+            col("topic1").is_not_null() +
+            col("topic2").is_not_null() +
+            col("topic3").is_not_null()).alias("num_indexed_args"))
+        .collect()?;
+    let logs_2 = resolve_log_topic0_collisions(logs_2, abi_df.clone(), topic0_alias.as_str(), data_alias.as_str(), mode)?;
+
+    // Split the logs into matched and not matched in the second step
+    let logs_topic0_matched = logs_2.clone().lazy().filter(col("full_signature").is_not_null()).collect()?;
+    let logs_topic0_not_matched = logs_2.lazy().filter(col("full_signature").is_null()).select(log_df_cols.clone());
+
+    // Anonymous events carry no signature hash in topic0, so topic0 itself is an indexed
+    // argument: `num_indexed_args` is recomputed here without the `+1` used above, and
+    // candidates are drawn only from the anonymous subset of the ABI DB.
+    let address_alias = get_config().log_decoder.log_schema.log_alias.address;
+    let anonymous_abi_df = abi_df.lazy().filter(col("anonymous"));
+
+    // create an abi_df with the most frequent anonymous signature for each address and indexed arg count
+    let anonymous_abi_by_address = anonymous_abi_df
+        .clone()
+        .group_by(["address", "full_signature", "name", "anonymous", "num_indexed_args"])
+        .agg([all().first(), len().alias("signature_count")])
+        .sort("signature_count", SortOptions {
+            descending: true,
+            nulls_last: true,
+            ..Default::default()}
+        )
+        .group_by(["address", "num_indexed_args"]).agg([
+            all().first()
+        ]).drop(["hash", "signature_count"]);
+
+    // create an abi_df with the most frequent anonymous signature for each indexed arg count, regardless of address
+    let anonymous_abi_by_indexed_args = anonymous_abi_df
+        .group_by(["num_indexed_args", "full_signature", "name", "anonymous"])
         .agg([all().first(), len().alias("signature_count")])
-        //sort the rows by signature_count in descending order
         .sort("signature_count", SortOptions {
             descending: true,
             nulls_last: true,
             ..Default::default()}
         )
-        // group by hash and num_indexed_args and keep the first row (most frequent hash and num_indexed_args)
-        .group_by(["hash", "num_indexed_args"]).agg([
+        .group_by(["num_indexed_args"]).agg([
             all().first()
-        ]).drop(["address", "signature_count"]);
+        ]).drop(["hash", "address", "signature_count"]);
+
+    let logs_3 = logs_topic0_not_matched
+        .with_column((lit(0 as u32) +
+            col(topic0_alias.as_str()).is_not_null() +
+            col("topic1").is_not_null() +
+            col("topic2").is_not_null() +
+            col("topic3").is_not_null()).alias("num_indexed_args"))
+        // Perform left join with the most frequent anonymous signature for the same address and indexed arg count
+        .join(
+            anonymous_abi_by_address,
+            [col(address_alias.as_str()), col("num_indexed_args")],
+            [col("address"), col("num_indexed_args")],
+            JoinArgs::new(JoinType::Left),
+        )
+        .collect()?;
+
+    // Split the logs into matched and not matched in the third step
+    let logs_anonymous_address_matched = logs_3.clone().lazy().filter(col("full_signature").is_not_null())
+        .with_column(lit("address").alias("match_source"))
+        .collect()?;
+    let logs_anonymous_address_not_matched = logs_3.lazy().filter(col("full_signature").is_null()).select(log_df_cols);
+
+    // Final fallback: match anonymous events by indexed arg count alone, regardless of address
+    let logs_4 = logs_anonymous_address_not_matched
+        .with_column((lit(0 as u32) +
+            col(topic0_alias.as_str()).is_not_null() +
+            col("topic1").is_not_null() +
+            col("topic2").is_not_null() +
+            col("topic3").is_not_null()).alias("num_indexed_args"))
+        // Perform left join with the most frequent anonymous signature for the same indexed arg count
+        .join(
+            anonymous_abi_by_indexed_args,
+            [col("num_indexed_args")],
+            [col("num_indexed_args")],
+            JoinArgs::new(JoinType::Left),
+        )
+        .with_column(
+            when(col("full_signature").is_not_null())
+                .then(lit("frequency"))
+                .otherwise(lit(NULL))
+                .alias("match_source"),
+        )
+        .collect()?;
+
+    // Combine all matching steps
+    let logs_df = logs_address_matched
+        .vstack(&logs_topic0_matched)?
+        .vstack(&logs_anonymous_address_matched)?
+        .vstack(&logs_4)?;
+
+    Ok(logs_df)
+}
+
+/// Resolves a topic0 collision for the logs still unmatched after `match_logs_by_topic0_address`,
+/// adding a `match_source` column alongside the usual ABI columns.
+fn resolve_log_topic0_collisions(
+    logs_unmatched: DataFrame,
+    abi_df: DataFrame,
+    topic0_alias: &str,
+    data_alias: &str,
+    mode: MatchMode,
+) -> Result<DataFrame, MatcherError> {
+    match mode {
+        MatchMode::Frequency => {
+            // create an abi_df with the most frequent signature for each hash
+            let topic0_abi_df = abi_df
+                .lazy()
+                //count the number of rows for each full_signature
+                .group_by(["hash", "full_signature", "name", "anonymous", "num_indexed_args"])
+                .agg([all().first(), len().alias("signature_count")])
+                //sort the rows by signature_count in descending order
+                .sort("signature_count", SortOptions {
+                    descending: true,
+                    nulls_last: true,
+                    ..Default::default()}
+                )
+                // group by hash and num_indexed_args and keep the first row (most frequent hash and num_indexed_args)
+                .group_by(["hash", "num_indexed_args"]).agg([
+                    all().first()
+                ]).drop(["address", "signature_count"]);
 
+            let logs = logs_unmatched
+                .lazy()
+                // Perform left join with the most frequent signature for each hash that has the same number of indexed args
+                .join(
+                    topic0_abi_df,
+                    [col(topic0_alias), col("num_indexed_args")],
+                    [col("hash"), col("num_indexed_args")],
+                    JoinArgs::new(JoinType::Left),
+                )
+                .with_column(
+                    when(col("full_signature").is_not_null())
+                        .then(lit("frequency"))
+                        .otherwise(lit(NULL))
+                        .alias("match_source"),
+                )
+                .collect()?;
+
+            Ok(logs)
+        }
+        MatchMode::Decode => {
+            resolve_by_decoding(logs_unmatched, abi_df, topic0_alias, "num_indexed_args", data_alias, true)
+        }
+    }
+}
+
+/// Matches logs with ABI signatures, keeping every candidate signature instead of collapsing a
+/// topic0 collision to one.
+///
+/// First attempts to match logs using both topic0 and address, exactly as
+/// `match_logs_by_topic0_address`; these rows get `candidate_rank` 1 and `match_confidence` 1.0.
+/// For logs still unmatched, every ABI signature sharing the log's topic0 and
+/// `num_indexed_args` is joined in, one row per candidate, annotated with:
+/// - `candidate_rank` - the candidate's rank by `signature_count`, 1 being most frequent
+/// - `signature_count` - how often that candidate appears in `abi_df` for the hash
+/// - `match_confidence` - `signature_count` divided by the summed `signature_count` of every
+///   candidate for the same hash and `num_indexed_args`
+///
+/// Unlike `match_logs_by_topic0`, this does not attempt anonymous-event matching: callers who
+/// need every candidate for a regular topic0 collision can build their own disambiguation or
+/// decoding pipeline on top of the exploded result.
+///
+/// # Arguments
+/// * `log_df` - DataFrame containing log entries
+/// * `abi_df` - DataFrame containing ABI signatures
+///
+/// # Returns
+/// * `Result<DataFrame>` - DataFrame with one row per candidate match, or error
+pub fn match_logs_all_candidates(log_df: DataFrame, abi_df: DataFrame) -> Result<DataFrame, MatcherError> {
+    let log_df_cols: Vec<Expr> = log_df.get_columns().iter().map(|s| col(s.name())).collect();
+    let logs_1 = match_logs_by_topic0_address(log_df, abi_df.clone())?;
+    match_logs_all_candidates_continue(logs_1, log_df_cols, abi_df)
+}
+
+/// Same as `match_logs_all_candidates`, but first scopes `log_df` to `filter`, so the join only
+/// runs over the contracts/events/block range the caller cares about, with the predicate fused
+/// into the same lazy pipeline as the address-matching join.
+///
+/// # Arguments
+/// * `log_df` - DataFrame containing log entries
+/// * `abi_df` - DataFrame containing ABI signatures
+/// * `filter` - predicate applied to `log_df` before matching
+///
+/// # Returns
+/// * `Result<DataFrame>` - DataFrame with one row per candidate match, or error
+pub fn match_logs_all_candidates_with_filter(log_df: DataFrame, abi_df: DataFrame, filter: &LogFilter) -> Result<DataFrame, MatcherError> {
+    let log_df_cols: Vec<Expr> = log_df.get_columns().iter().map(|s| col(s.name())).collect();
+    let logs_1 = match_logs_by_topic0_address_lazy(filter.apply(log_df.lazy()), abi_df.clone()).collect()?;
+    match_logs_all_candidates_continue(logs_1, log_df_cols, abi_df)
+}
+
+/// Shared tail of `match_logs_all_candidates`/`match_logs_all_candidates_with_filter`: explodes
+/// every candidate for the logs still unmatched after the already-computed address-matching step
+/// `logs_1`.
+fn match_logs_all_candidates_continue(logs_1: DataFrame, log_df_cols: Vec<Expr>, abi_df: DataFrame) -> Result<DataFrame, MatcherError> {
+    let logs_address_matched = logs_1.clone().lazy().filter(col("full_signature").is_not_null())
+        .with_column(lit(1 as u32).alias("candidate_rank"))
+        .with_column(lit(NULL).cast(DataType::UInt32).alias("signature_count"))
+        .with_column(lit(1.0 as f64).alias("match_confidence"))
+        .collect()?;
+    let logs_address_not_matched = logs_1.lazy().filter(col("full_signature").is_null()).select(log_df_cols);
+
+    // every candidate sharing a hash/num_indexed_args pair, ranked and scored instead of
+    // collapsed to the single most frequent one
     let topic0_alias = get_config().log_decoder.log_schema.log_alias.topic0;
-    // add a column with the number of indexed args
+    let abi_candidates = abi_df
+        .lazy()
+        .group_by(["hash", "num_indexed_args", "full_signature", "name", "anonymous"])
+        .agg([all().first(), len().alias("signature_count")])
+        .with_column(col("signature_count").sum().over(["hash", "num_indexed_args"]).alias("total_count"))
+        .with_column((col("signature_count").cast(DataType::Float64) / col("total_count").cast(DataType::Float64)).alias("match_confidence"))
+        .with_column(
+            col("signature_count")
+                .rank(RankOptions { method: RankMethod::Ordinal, descending: true, ..Default::default() }, None)
+                .over(["hash", "num_indexed_args"])
+                .cast(DataType::UInt32)
+                .alias("candidate_rank"),
+        )
+        .drop(["address", "total_count"]);
+
+    // `logs_address_matched` and the joined `abi_candidates` build up candidate_rank,
+    // signature_count and match_confidence in different orders; re-select to
+    // `logs_address_matched`'s column order so the two line up for vstack.
+    let match_cols: Vec<Expr> = logs_address_matched.get_column_names().into_iter().map(col).collect();
     let logs_2 = logs_address_not_matched
         .with_column((lit(1 as u32) +
             col("topic1").is_not_null() +
             col("topic2").is_not_null() +
             col("topic3").is_not_null()).alias("num_indexed_args"))
-        // Perform left join with the most frequent signature for each hash that has the same number of indexed args
+        // Perform left join bringing in every candidate sharing the same hash and indexed arg count
         .join(
-            abi_df,
+            abi_candidates,
             [col(topic0_alias.as_str()), col("num_indexed_args")],
             [col("hash"), col("num_indexed_args")],
             JoinArgs::new(JoinType::Left),
         )
+        .select(match_cols)
         .collect()?;
 
-    // Combine first and second matching steps
     let logs_df = logs_address_matched.vstack(&logs_2)?;
 
     Ok(logs_df)
@@ -120,26 +481,48 @@ pub fn match_logs_by_topic0(log_df: DataFrame, abi_df: DataFrame) -> Result<Data
 /// # Returns
 /// * `Result<DataFrame>` - Joined DataFrame with matched signatures, or error
 pub fn match_traces_by_4bytes_address(trace_df: DataFrame, abi_df: DataFrame) -> Result<DataFrame, MatcherError> {
+    Ok(match_traces_by_4bytes_address_lazy(trace_df.lazy(), abi_df).collect()?)
+}
+
+/// Lazy core of `match_traces_by_4bytes_address`, kept separate so a caller-supplied predicate
+/// (e.g. `TraceFilter`) can be fused into the same lazy pipeline as the join instead of being
+/// collected beforehand.
+fn match_traces_by_4bytes_address_lazy(trace_df: LazyFrame, abi_df: DataFrame) -> LazyFrame {
     let selector_alias = get_config().trace_decoder.trace_schema.trace_alias.selector;
     let action_to = get_config().trace_decoder.trace_schema.trace_alias.action_to;
 
-    let traces_left_join_abi_df = trace_df
-        .lazy()
+    trace_df
         .join(
             abi_df.lazy(),
             [col(selector_alias.as_str()), col(action_to.as_str())],
             [col("hash"), col("address")],
             JoinArgs::new(JoinType::Left),
         )
-        .collect()?;
+}
 
-    Ok(traces_left_join_abi_df)
+/// Same as `match_traces_by_4bytes_address`, but first scopes `trace_df` to `filter`, so the
+/// join only runs over the contracts/selectors/block range the caller cares about.
+///
+/// # Arguments
+/// * `trace_df` - DataFrame containing trace entries
+/// * `abi_df` - DataFrame containing ABI signatures
+/// * `filter` - predicate applied to `trace_df` before matching
+///
+/// # Returns
+/// * `Result<DataFrame>` - Joined DataFrame with matched signatures, or error
+pub fn match_traces_by_4bytes_address_with_filter(trace_df: DataFrame, abi_df: DataFrame, filter: &TraceFilter) -> Result<DataFrame, MatcherError> {
+    Ok(match_traces_by_4bytes_address_lazy(filter.apply(trace_df.lazy()), abi_df).collect()?)
 }
 
 /// Matches traces with ABI signatures using a two-step matching process.
 ///
 /// First attempts to match traces using both 4-byte selector and address. For unmatched traces,
-/// tries matching only by 4-byte selector using the most frequent signature for each hash.
+/// tries matching only by 4-byte selector, resolving any collision between candidate signatures
+/// according to `mode`.
+///
+/// A `match_source` column is added to the result: `"address"` for traces matched in the first
+/// step, and `"decoded"`/`"frequency"` for traces matched in the selector-only step (depending
+/// on `mode` and whether a collision was resolved by decoding).
 ///
 /// # Arguments
 /// * `trace_df` - DataFrame containing trace entries
@@ -148,39 +531,754 @@ pub fn match_traces_by_4bytes_address(trace_df: DataFrame, abi_df: DataFrame) ->
 /// # Returns
 /// * `Result<DataFrame>` - DataFrame with matched signatures, or error
 pub fn match_traces_by_4bytes(trace_df: DataFrame, abi_df: DataFrame) -> Result<DataFrame, MatcherError> {
-    let traces_1 = match_traces_by_4bytes_address(trace_df.clone(), abi_df.clone())?;
+    match_traces_by_4bytes_with_mode(trace_df, abi_df, MatchMode::default())
+}
+
+/// Same as `match_traces_by_4bytes`, but resolves a 4-byte-selector collision between several
+/// candidate signatures according to `mode` instead of always picking the most frequent one.
+///
+/// # Arguments
+/// * `trace_df` - DataFrame containing trace entries
+/// * `abi_df` - DataFrame containing ABI signatures
+/// * `mode` - How to resolve a 4-byte-selector collision between several candidate signatures
+///
+/// # Returns
+/// * `Result<DataFrame>` - DataFrame with matched signatures, or error
+pub fn match_traces_by_4bytes_with_mode(trace_df: DataFrame, abi_df: DataFrame, mode: MatchMode) -> Result<DataFrame, MatcherError> {
+    let trace_df_cols: Vec<Expr> = trace_df.get_columns().iter().map(|s| col(s.name())).collect();
+    let traces_1 = match_traces_by_4bytes_address(trace_df, abi_df.clone())?;
+    match_traces_by_4bytes_continue(traces_1, trace_df_cols, abi_df, mode)
+}
+
+/// Same as `match_traces_by_4bytes_with_mode`, but first scopes `trace_df` to `filter`, so the
+/// join only runs over the contracts/selectors/block range the caller cares about, with the
+/// predicate fused into the same lazy pipeline as the address-matching join.
+///
+/// # Arguments
+/// * `trace_df` - DataFrame containing trace entries
+/// * `abi_df` - DataFrame containing ABI signatures
+/// * `mode` - How to resolve a 4-byte-selector collision between several candidate signatures
+/// * `filter` - predicate applied to `trace_df` before matching
+///
+/// # Returns
+/// * `Result<DataFrame>` - DataFrame with matched signatures, or error
+pub fn match_traces_by_4bytes_with_filter(trace_df: DataFrame, abi_df: DataFrame, mode: MatchMode, filter: &TraceFilter) -> Result<DataFrame, MatcherError> {
     let trace_df_cols: Vec<Expr> = trace_df.get_columns().iter().map(|s| col(s.name())).collect();
-    let traces_address_matched = traces_1.clone().lazy().filter(col("full_signature").is_not_null()).collect()?;
+    let traces_1 = match_traces_by_4bytes_address_lazy(filter.apply(trace_df.lazy()), abi_df.clone()).collect()?;
+    match_traces_by_4bytes_continue(traces_1, trace_df_cols, abi_df, mode)
+}
+
+/// Shared tail of `match_traces_by_4bytes`/`match_traces_by_4bytes_with_filter`: resolves the
+/// selector collision against the already-computed address-matching step `traces_1`.
+fn match_traces_by_4bytes_continue(traces_1: DataFrame, trace_df_cols: Vec<Expr>, abi_df: DataFrame, mode: MatchMode) -> Result<DataFrame, MatcherError> {
+    let traces_address_matched = traces_1.clone().lazy().filter(col("full_signature").is_not_null())
+        .with_column(lit("address").alias("match_source"))
+        .collect()?;
+    let traces_address_not_matched = traces_1.lazy().filter(col("full_signature").is_null()).select(trace_df_cols).collect()?;
+
+    let selector_alias = get_config().trace_decoder.trace_schema.trace_alias.selector;
+    let action_input_alias = get_config().trace_decoder.trace_schema.trace_alias.action_input;
+    let trace_2 = resolve_trace_selector_collisions(traces_address_not_matched, abi_df, selector_alias.as_str(), action_input_alias.as_str(), mode)?;
+
+    let traces_df = traces_address_matched.vstack(&trace_2)?;
+
+    Ok(traces_df)
+}
+
+/// Resolves a 4-byte-selector collision for the traces still unmatched after
+/// `match_traces_by_4bytes_address`, adding a `match_source` column alongside the usual ABI columns.
+fn resolve_trace_selector_collisions(
+    traces_unmatched: DataFrame,
+    abi_df: DataFrame,
+    selector_alias: &str,
+    action_input_alias: &str,
+    mode: MatchMode,
+) -> Result<DataFrame, MatcherError> {
+    match mode {
+        MatchMode::Frequency => {
+            // create an abi_df with the most frequent signature for each hash
+            let abi_df = abi_df
+                .lazy()
+                //count the number of rows for each full_signature
+                .group_by(["hash", "full_signature", "name"])
+                .agg([all().first(), len().alias("signature_count")])
+                //sort the rows by signature_count in descending order
+                .sort("signature_count", SortOptions {
+                    descending: true,
+                    nulls_last: true,
+                    ..Default::default()}
+                )
+                // group by hash and keep the first row (most frequent signature for each hash)
+                .group_by(["hash"]).agg([
+                    all().first()
+                ]).drop(["address", "signature_count"]);
+
+            let traces = traces_unmatched
+                .lazy()
+                .join(
+                    abi_df,
+                    [col(selector_alias)],
+                    [col("hash")],
+                    JoinArgs::new(JoinType::Left),
+                )
+                .with_column(
+                    when(col("full_signature").is_not_null())
+                        .then(lit("frequency"))
+                        .otherwise(lit(NULL))
+                        .alias("match_source"),
+                )
+                .collect()?;
+
+            Ok(traces)
+        }
+        MatchMode::Decode => {
+            resolve_by_decoding(traces_unmatched, abi_df, selector_alias, "", action_input_alias, false)
+        }
+    }
+}
+
+/// Matches traces with ABI signatures, keeping every candidate signature instead of collapsing
+/// a 4-byte-selector collision to one. See [`match_logs_all_candidates`] for the column
+/// semantics (`candidate_rank`, `signature_count`, `match_confidence`); address-matched traces
+/// get `candidate_rank` 1 and `match_confidence` 1.0.
+///
+/// # Arguments
+/// * `trace_df` - DataFrame containing trace entries
+/// * `abi_df` - DataFrame containing ABI signatures
+///
+/// # Returns
+/// * `Result<DataFrame>` - DataFrame with one row per candidate match, or error
+pub fn match_traces_all_candidates(trace_df: DataFrame, abi_df: DataFrame) -> Result<DataFrame, MatcherError> {
+    let trace_df_cols: Vec<Expr> = trace_df.get_columns().iter().map(|s| col(s.name())).collect();
+    let traces_1 = match_traces_by_4bytes_address(trace_df, abi_df.clone())?;
+    match_traces_all_candidates_continue(traces_1, trace_df_cols, abi_df)
+}
+
+/// Same as `match_traces_all_candidates`, but first scopes `trace_df` to `filter`, so the join
+/// only runs over the contracts/selectors/block range the caller cares about, with the predicate
+/// fused into the same lazy pipeline as the address-matching join.
+///
+/// # Arguments
+/// * `trace_df` - DataFrame containing trace entries
+/// * `abi_df` - DataFrame containing ABI signatures
+/// * `filter` - predicate applied to `trace_df` before matching
+///
+/// # Returns
+/// * `Result<DataFrame>` - DataFrame with one row per candidate match, or error
+pub fn match_traces_all_candidates_with_filter(trace_df: DataFrame, abi_df: DataFrame, filter: &TraceFilter) -> Result<DataFrame, MatcherError> {
+    let trace_df_cols: Vec<Expr> = trace_df.get_columns().iter().map(|s| col(s.name())).collect();
+    let traces_1 = match_traces_by_4bytes_address_lazy(filter.apply(trace_df.lazy()), abi_df.clone()).collect()?;
+    match_traces_all_candidates_continue(traces_1, trace_df_cols, abi_df)
+}
+
+/// Shared tail of `match_traces_all_candidates`/`match_traces_all_candidates_with_filter`:
+/// explodes every candidate for the traces still unmatched after the already-computed
+/// address-matching step `traces_1`.
+fn match_traces_all_candidates_continue(traces_1: DataFrame, trace_df_cols: Vec<Expr>, abi_df: DataFrame) -> Result<DataFrame, MatcherError> {
+    let traces_address_matched = traces_1.clone().lazy().filter(col("full_signature").is_not_null())
+        .with_column(lit(1 as u32).alias("candidate_rank"))
+        .with_column(lit(NULL).cast(DataType::UInt32).alias("signature_count"))
+        .with_column(lit(1.0 as f64).alias("match_confidence"))
+        .collect()?;
     let traces_address_not_matched = traces_1.lazy().filter(col("full_signature").is_null()).select(trace_df_cols);
 
-    // create an abi_df with the most frequent signature for each hash
-    let abi_df = abi_df
+    let abi_candidates = abi_df
         .lazy()
-        //count the number of rows for each full_signature
         .group_by(["hash", "full_signature", "name"])
         .agg([all().first(), len().alias("signature_count")])
-        //sort the rows by signature_count in descending order
-        .sort("signature_count", SortOptions {
-            descending: true,
-            nulls_last: true,
-            ..Default::default()}
+        .with_column(col("signature_count").sum().over(["hash"]).alias("total_count"))
+        .with_column((col("signature_count").cast(DataType::Float64) / col("total_count").cast(DataType::Float64)).alias("match_confidence"))
+        .with_column(
+            col("signature_count")
+                .rank(RankOptions { method: RankMethod::Ordinal, descending: true, ..Default::default() }, None)
+                .over(["hash"])
+                .cast(DataType::UInt32)
+                .alias("candidate_rank"),
         )
-        // group by hash and num_indexed_args and keep the first row (most frequent hash and num_indexed_args)
-        .group_by(["hash"]).agg([
-            all().first()
-        ]).drop(["address", "signature_count"]);
+        .drop(["address", "total_count"]);
 
+    // `traces_address_matched` and the joined `abi_candidates` build up candidate_rank,
+    // signature_count and match_confidence in different orders; re-select to
+    // `traces_address_matched`'s column order so the two line up for vstack.
+    let match_cols: Vec<Expr> = traces_address_matched.get_column_names().into_iter().map(col).collect();
     let selector_alias = get_config().trace_decoder.trace_schema.trace_alias.selector;
     let trace_2 = traces_address_not_matched
-            .join(
-                abi_df,
-                [col(selector_alias.as_str())],
-                [col("hash")],
-                JoinArgs::new(JoinType::Left),
-            )
-            .collect()?;
+        // Perform left join bringing in every candidate sharing the same selector
+        .join(
+            abi_candidates,
+            [col(selector_alias.as_str())],
+            [col("hash")],
+            JoinArgs::new(JoinType::Left),
+        )
+        .select(match_cols)
+        .collect()?;
 
     let traces_df = traces_address_matched.vstack(&trace_2)?;
 
     Ok(traces_df)
 }
+
+/// Shared row-by-row decode-disambiguation used by both the log and trace matchers: for each
+/// row, gathers every `abi_df` candidate sharing its hash/selector (and, for logs, the same
+/// `num_indexed_args`), tries to ABI-decode the row's payload against each candidate, and keeps
+/// whichever decodes cleanly.
+///
+/// * `key_col` - column in both `rows` and `abi_df` to match the hash/selector on
+/// * `extra_key_col` - an additional exact-match column (`num_indexed_args`), or `""` for traces
+/// * `payload_col` - column holding the hex-encoded payload to decode (log `data` or trace calldata)
+/// * `is_log` - whether non-indexed parameter types should be derived (logs) or all parameter
+///   types used directly (traces, which have no indexed concept) and whether the payload's
+///   leading 4-byte selector should be skipped (traces)
+fn resolve_by_decoding(
+    rows: DataFrame,
+    abi_df: DataFrame,
+    key_col: &str,
+    extra_key_col: &str,
+    payload_col: &str,
+    is_log: bool,
+) -> Result<DataFrame, MatcherError> {
+    let candidate_cols: Vec<&str> = if extra_key_col.is_empty() {
+        vec!["hash", "full_signature", "name"]
+    } else {
+        vec!["hash", extra_key_col, "full_signature", "name", "anonymous"]
+    };
+    let candidates = abi_df
+        .lazy()
+        .group_by(candidate_cols)
+        .agg([all().first(), len().alias("signature_count")])
+        .collect()?;
+
+    let row_keys = rows.column(key_col)?.str()?.clone();
+    let row_extra_keys = if extra_key_col.is_empty() {
+        None
+    } else {
+        Some(rows.column(extra_key_col)?.u32()?.clone())
+    };
+    let row_payloads = rows.column(payload_col)?.str()?.clone();
+
+    let cand_hashes = candidates.column("hash")?.str()?.clone();
+    let cand_extra_keys = if extra_key_col.is_empty() {
+        None
+    } else {
+        Some(candidates.column(extra_key_col)?.u32()?.clone())
+    };
+    let cand_full_signature = candidates.column("full_signature")?.str()?.clone();
+    let cand_signature_count = candidates.column("signature_count")?.idx()?.clone();
+
+    let height = rows.height();
+    let mut out_full_signature: Vec<Option<&str>> = Vec::with_capacity(height);
+    let mut out_match_source: Vec<Option<&str>> = Vec::with_capacity(height);
+    let mut matched_rows: Vec<usize> = Vec::with_capacity(height);
+
+    for i in 0..height {
+        let key = row_keys.get(i);
+        let extra_key = row_extra_keys.as_ref().map(|c| c.get(i));
+        let payload = row_payloads.get(i).and_then(decode_hex);
+
+        let matching: Vec<usize> = (0..candidates.height())
+            .filter(|&j| {
+                cand_hashes.get(j) == key
+                    && match (&extra_key, &cand_extra_keys) {
+                        (Some(ek), Some(ck)) => *ek == ck.get(j),
+                        _ => true,
+                    }
+            })
+            .collect();
+
+        let decoded: Vec<usize> = match &payload {
+            Some(bytes) => {
+                let bytes = if is_log { bytes.as_slice() } else { bytes.get(4..).unwrap_or(&[]) };
+                matching
+                    .iter()
+                    .copied()
+                    .filter(|&j| {
+                        let signature = cand_full_signature.get(j).unwrap_or_default();
+                        // `extra_key` is the log's topic count (topic0 plus every present
+                        // indexed topic), not the indexed-parameter count: topic0 holds the
+                        // signature hash, not a parameter, so it must be subtracted off here.
+                        let num_topics = extra_key.flatten().unwrap_or(0) as usize;
+                        let types = if is_log {
+                            parse_non_indexed_param_types(signature, num_topics.saturating_sub(1))
+                        } else {
+                            parse_param_types(signature)
+                        };
+                        types.is_some_and(|types| decodes_cleanly(bytes, &types))
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        let chosen = if decoded.len() == 1 {
+            Some(decoded[0])
+        } else if decoded.len() > 1 {
+            decoded.into_iter().max_by_key(|&j| cand_signature_count.get(j).unwrap_or(0))
+        } else {
+            None
+        };
+
+        match chosen {
+            Some(j) => {
+                out_full_signature.push(cand_full_signature.get(j));
+                out_match_source.push(Some("decoded"));
+                matched_rows.push(j);
+            }
+            None => {
+                out_full_signature.push(None);
+                out_match_source.push(None);
+                matched_rows.push(usize::MAX);
+            }
+        }
+    }
+
+    let matched_signature = Series::new("full_signature", out_full_signature);
+    let match_source = Series::new("match_source", out_match_source);
+
+    let mut rows = rows;
+    rows.with_column(matched_signature)?;
+
+    // Bring in the remaining ABI columns (name, anonymous, etc.) for each chosen candidate, if
+    // any, excluding `hash` and `address` (the row already has its own, and re-attaching the
+    // candidate's would silently overwrite it) and any key already present on the row. This
+    // mirrors the Frequency branch's schema and order exactly, so the two branches vstack.
+    let candidate_idx: Vec<Option<IdxSize>> = matched_rows
+        .iter()
+        .map(|&j| if j == usize::MAX { None } else { Some(j as IdxSize) })
+        .collect();
+    let candidate_idx = IdxCa::from_slice_options("", &candidate_idx);
+    let other_abi_cols: Vec<&str> = candidates
+        .get_column_names()
+        .into_iter()
+        .filter(|name| !["hash", "address", "full_signature", "signature_count", extra_key_col].contains(name))
+        .collect();
+    for name in other_abi_cols {
+        let taken = candidates.column(name)?.take(&candidate_idx)?.with_name(name).clone();
+        rows.with_column(taken)?;
+    }
+
+    rows.with_column(match_source)?;
+
+    Ok(rows)
+}
+
+/// Decodes a `0x`-prefixed (or bare) hex string into raw bytes, or `None` if it is malformed.
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// A minimal structural view of a Solidity ABI parameter type -- just detailed enough to
+/// validate whether a payload's head/tail layout is internally consistent.
+#[derive(Debug, Clone)]
+enum AbiType {
+    /// A type that is always encoded inline in the head, taking up `head_words` 32-byte words.
+    Static { head_words: usize },
+    /// `bytes` or `string`: a length-prefixed byte string referenced via an offset.
+    Bytes,
+    /// `T[]`: a length-prefixed, variable-length array referenced via an offset.
+    DynArray(Box<AbiType>),
+    /// `T[N]`: a fixed-length array, dynamic only if its element type is dynamic.
+    FixedArray(Box<AbiType>, usize),
+    /// `(T1, T2, ...)`: a tuple, dynamic if any member is dynamic.
+    Tuple(Vec<AbiType>),
+}
+
+impl AbiType {
+    fn is_dynamic(&self) -> bool {
+        match self {
+            AbiType::Static { .. } => false,
+            AbiType::Bytes | AbiType::DynArray(_) => true,
+            AbiType::FixedArray(elem, _) => elem.is_dynamic(),
+            AbiType::Tuple(members) => members.iter().any(AbiType::is_dynamic),
+        }
+    }
+
+    fn head_words(&self) -> usize {
+        match self {
+            AbiType::Static { head_words } => *head_words,
+            AbiType::Bytes | AbiType::DynArray(_) => 1,
+            AbiType::FixedArray(elem, len) => {
+                if self.is_dynamic() {
+                    1
+                } else {
+                    elem.head_words() * len
+                }
+            }
+            AbiType::Tuple(members) => {
+                if self.is_dynamic() {
+                    1
+                } else {
+                    members.iter().map(AbiType::head_words).sum()
+                }
+            }
+        }
+    }
+}
+
+/// Splits a comma-separated type list at the top level only, so that commas nested inside
+/// tuple types (e.g. `(uint8,(bool,bytes))`) are not treated as separators.
+fn split_top_level(types: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in types.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(types[start..i].to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < types.len() {
+        parts.push(types[start..].to_string());
+    }
+    parts
+}
+
+/// Parses a Solidity type name (e.g. `uint256`, `bytes`, `address[]`, `(uint8,bool)`) into its
+/// structural [`AbiType`]. Unrecognized or malformed type strings are treated as a single
+/// static word, so that decoding simply fails to line up rather than panicking.
+fn parse_abi_type(ty: &str) -> AbiType {
+    let ty = ty.trim();
+    if let Some(inner) = ty.strip_suffix("[]") {
+        return AbiType::DynArray(Box::new(parse_abi_type(inner)));
+    }
+    if let Some(open) = ty.rfind('[') {
+        if ty.ends_with(']') {
+            if let Ok(len) = ty[open + 1..ty.len() - 1].parse::<usize>() {
+                return AbiType::FixedArray(Box::new(parse_abi_type(&ty[..open])), len);
+            }
+        }
+    }
+    if let Some(inner) = ty.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        return AbiType::Tuple(split_top_level(inner).iter().map(|s| parse_abi_type(s)).collect());
+    }
+    match ty {
+        "bytes" | "string" => AbiType::Bytes,
+        _ => AbiType::Static { head_words: 1 },
+    }
+}
+
+/// Parameter types for a function's `full_signature`, in declaration order.
+fn parse_param_types(full_signature: &str) -> Option<Vec<AbiType>> {
+    let open = full_signature.find('(')?;
+    let close = full_signature.rfind(')')?;
+    let params = split_top_level(&full_signature[open + 1..close]);
+    if params.len() == 1 && params[0].is_empty() {
+        return Some(Vec::new());
+    }
+    Some(params.iter().map(|p| parse_abi_type(p)).collect())
+}
+
+/// Parameter types for the portion of an event's `full_signature` that is encoded in `data`
+/// rather than in topics. `full_signature` lists every parameter (indexed and non-indexed) in
+/// the order needed to compute the event hash; lacking per-parameter indexed positions, the
+/// last `total - num_indexed_args` parameters are assumed to be the non-indexed ones, which
+/// holds whenever indexed parameters are declared first.
+fn parse_non_indexed_param_types(full_signature: &str, num_indexed_args: usize) -> Option<Vec<AbiType>> {
+    let open = full_signature.find('(')?;
+    let close = full_signature.rfind(')')?;
+    let params = split_top_level(&full_signature[open + 1..close]);
+    if params.len() == 1 && params[0].is_empty() {
+        return Some(Vec::new());
+    }
+    let skip = num_indexed_args.min(params.len());
+    Some(params[skip..].iter().map(|p| parse_abi_type(p)).collect())
+}
+
+/// Checks whether `data` is a valid ABI encoding of `types`: the static head must exactly cover
+/// `data` when there are no dynamic members, and every dynamic offset must point inside `data`
+/// at a position whose declared length also fits inside `data`, with the last dynamic segment
+/// ending exactly at `data`'s length (no short or trailing bytes).
+fn decodes_cleanly(data: &[u8], types: &[AbiType]) -> bool {
+    let head_size: usize = types.iter().map(AbiType::head_words).sum::<usize>() * 32;
+    if data.len() < head_size {
+        return false;
+    }
+    if !types.iter().any(AbiType::is_dynamic) {
+        return data.len() == head_size;
+    }
+
+    let mut head_pos = 0;
+    let mut tail_end = head_size;
+    for ty in types {
+        let words = ty.head_words();
+        if ty.is_dynamic() {
+            match read_usize(data, head_pos).and_then(|offset| tail_extent(data, offset, ty)) {
+                Some(extent) => tail_end = tail_end.max(extent),
+                None => return false,
+            }
+        }
+        head_pos += words * 32;
+    }
+    tail_end == data.len()
+}
+
+/// Returns the byte index one past the end of the tail content for the dynamic `ty` starting at
+/// `offset`, or `None` if a declared length or nested offset runs past `data`.
+fn tail_extent(data: &[u8], offset: usize, ty: &AbiType) -> Option<usize> {
+    match ty {
+        AbiType::Bytes => {
+            let len = read_usize(data, offset)?;
+            let padded = len.div_ceil(32) * 32;
+            let end = offset.checked_add(32)?.checked_add(padded)?;
+            (end <= data.len()).then_some(end)
+        }
+        AbiType::DynArray(elem) => {
+            let len = read_usize(data, offset)?;
+            let elements_start = offset.checked_add(32)?;
+            if elem.is_dynamic() {
+                let mut end = elements_start.checked_add(len.checked_mul(32)?)?;
+                for i in 0..len {
+                    let elem_offset = read_usize(data, elements_start.checked_add(i.checked_mul(32)?)?)?;
+                    let elem_end = tail_extent(data, elements_start.checked_add(elem_offset)?, elem)?;
+                    end = end.max(elem_end);
+                }
+                (end <= data.len()).then_some(end)
+            } else {
+                let end = elements_start.checked_add(len.checked_mul(elem.head_words() * 32)?)?;
+                (end <= data.len()).then_some(end)
+            }
+        }
+        AbiType::FixedArray(elem, len) if elem.is_dynamic() => {
+            let mut end = offset.checked_add(len.checked_mul(32)?)?;
+            for i in 0..*len {
+                let elem_offset = read_usize(data, offset.checked_add(i.checked_mul(32)?)?)?;
+                let elem_end = tail_extent(data, offset.checked_add(elem_offset)?, elem)?;
+                end = end.max(elem_end);
+            }
+            (end <= data.len()).then_some(end)
+        }
+        AbiType::Tuple(members) if ty.is_dynamic() => {
+            let mut head_pos = offset;
+            let mut end = offset.checked_add(members.iter().map(AbiType::head_words).sum::<usize>() * 32)?;
+            for member in members {
+                let words = member.head_words();
+                if member.is_dynamic() {
+                    let member_offset = read_usize(data, head_pos)?;
+                    let member_end = tail_extent(data, offset.checked_add(member_offset)?, member)?;
+                    end = end.max(member_end);
+                }
+                head_pos += words * 32;
+            }
+            (end <= data.len()).then_some(end)
+        }
+        // Static types never go through the tail, but a defensive fallback keeps this total.
+        _ => offset.checked_add(ty.head_words() * 32).filter(|&e| e <= data.len()),
+    }
+}
+
+/// Reads a 32-byte big-endian word at `offset` as a `usize`, rejecting (rather than panicking
+/// on) values too large to represent -- real offsets/lengths always fit comfortably.
+fn read_usize(data: &[u8], offset: usize) -> Option<usize> {
+    let word = data.get(offset..offset.checked_add(32)?)?;
+    if word[..24].iter().any(|&b| b != 0) {
+        return None;
+    }
+    Some(u64::from_be_bytes(word[24..32].try_into().ok()?) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Anonymous events carry no signature hash in topic0, so `num_indexed_args` for an
+    /// anonymous ABI row is the raw indexed-argument count `k`, not `1 + k` as it is for regular
+    /// events. This pins that the log's recomputed topic count lines up against that raw count
+    /// in the anonymous-matching stage of `match_logs_by_topic0`.
+    #[test]
+    fn matches_anonymous_event_by_address_and_topic_count() {
+        let topic0_alias = get_config().log_decoder.log_schema.log_alias.topic0;
+        let address_alias = get_config().log_decoder.log_schema.log_alias.address;
+        let data_alias = get_config().log_decoder.log_schema.log_alias.data;
+
+        let address = "0xabc0000000000000000000000000000000000a";
+
+        // Two topics present (topic0 + topic1), neither of which is a signature hash -- this is
+        // what an anonymous event with two indexed parameters looks like on the wire.
+        let log_df = df![
+            address_alias.as_str() => [address],
+            topic0_alias.as_str() => ["0x1111111111111111111111111111111111111111111111111111111111111a"],
+            "topic1" => ["0x2222222222222222222222222222222222222222222222222222222222222b"],
+            "topic2" => [None::<&str>],
+            "topic3" => [None::<&str>],
+            data_alias.as_str() => ["0x"],
+        ].unwrap();
+
+        // The only ABI candidate is anonymous with num_indexed_args = 2 (the raw indexed
+        // count, not 1 + 2); it carries no hash at all, so it cannot match via the
+        // topic0-keyed steps.
+        let abi_df = df![
+            "hash" => [None::<&str>],
+            "address" => [address],
+            "full_signature" => ["AnonymousEvent(uint256,uint256)"],
+            "name" => ["AnonymousEvent"],
+            "anonymous" => [true],
+            "num_indexed_args" => [2_u32],
+        ].unwrap();
+
+        let matched = match_logs_by_topic0_with_mode(log_df, abi_df, MatchMode::Frequency).unwrap();
+
+        assert_eq!(matched.height(), 1);
+        assert_eq!(
+            matched.column("full_signature").unwrap().str().unwrap().get(0),
+            Some("AnonymousEvent(uint256,uint256)")
+        );
+        assert_eq!(
+            matched.column("match_source").unwrap().str().unwrap().get(0),
+            Some("address")
+        );
+    }
+
+    /// `num_indexed_args` on both sides of the topic0 join is the topic count (topic0 plus every
+    /// present indexed topic), not the indexed-parameter count -- topic0 holds the signature
+    /// hash, not a parameter. This pins that `resolve_by_decoding` accounts for that offset when
+    /// deriving which `full_signature` parameters are non-indexed, by setting up two candidates
+    /// that collide on hash and topic count but only one of which decodes `data` cleanly.
+    #[test]
+    fn resolves_topic0_collision_by_decoding_data() {
+        let topic0_alias = get_config().log_decoder.log_schema.log_alias.topic0;
+        let address_alias = get_config().log_decoder.log_schema.log_alias.address;
+        let data_alias = get_config().log_decoder.log_schema.log_alias.data;
+
+        let hash = "0x1111111111111111111111111111111111111111111111111111111111111a";
+
+        // One indexed topic present alongside topic0, so num_indexed_args (topic count) is 2;
+        // data holds two 32-byte words, matching only the candidate with one indexed parameter.
+        let log_df = df![
+            address_alias.as_str() => ["0xabc0000000000000000000000000000000000a"],
+            topic0_alias.as_str() => [hash],
+            "topic1" => ["0x2222222222222222222222222222222222222222222222222222222222222b"],
+            "topic2" => [None::<&str>],
+            "topic3" => [None::<&str>],
+            data_alias.as_str() => [format!("0x{}", "00".repeat(64))],
+        ].unwrap();
+
+        // Both candidates share hash and topic count, so step 1 (topic0 + address) and the
+        // frequency-based topic0 step can't tell them apart; only `Transfer(address,address,
+        // uint256)` (one indexed address, two non-indexed words) decodes `data` cleanly, since
+        // `Transfer(address,uint256)` expects a single non-indexed word.
+        let abi_df = df![
+            "hash" => [hash, hash],
+            "address" => ["0xdef0000000000000000000000000000000000d", "0xdef0000000000000000000000000000000000d"],
+            "full_signature" => ["Transfer(address,address,uint256)", "Transfer(address,uint256)"],
+            "name" => ["Transfer", "Transfer"],
+            "anonymous" => [false, false],
+            "num_indexed_args" => [2_u32, 2_u32],
+        ].unwrap();
+
+        let matched = match_logs_by_topic0_with_mode(log_df, abi_df, MatchMode::Decode).unwrap();
+
+        assert_eq!(matched.height(), 1);
+        assert_eq!(
+            matched.column("full_signature").unwrap().str().unwrap().get(0),
+            Some("Transfer(address,address,uint256)")
+        );
+        assert_eq!(
+            matched.column("match_source").unwrap().str().unwrap().get(0),
+            Some("decoded")
+        );
+    }
+
+    /// `match_logs_all_candidates` explodes every signature sharing a topic0/topic-count
+    /// collision instead of collapsing to one, annotated with `candidate_rank` (by frequency) and
+    /// `match_confidence` (its share of the hash's total occurrences). This pins both: the more
+    /// frequent candidate must rank 1 with the larger confidence, and the two confidences must
+    /// sum to 1.0.
+    #[test]
+    fn all_candidates_ranks_and_confidences_by_frequency() {
+        let topic0_alias = get_config().log_decoder.log_schema.log_alias.topic0;
+        let address_alias = get_config().log_decoder.log_schema.log_alias.address;
+        let data_alias = get_config().log_decoder.log_schema.log_alias.data;
+
+        let hash = "0x1111111111111111111111111111111111111111111111111111111111111a";
+
+        let log_df = df![
+            address_alias.as_str() => ["0xabc0000000000000000000000000000000000a"],
+            topic0_alias.as_str() => [hash],
+            "topic1" => [None::<&str>],
+            "topic2" => [None::<&str>],
+            "topic3" => [None::<&str>],
+            data_alias.as_str() => ["0x"],
+        ].unwrap();
+
+        // "Approval" appears twice as often as "Transfer" for this hash/topic-count pair, so it
+        // should come out with candidate_rank 1 and match_confidence 2/3.
+        let abi_df = df![
+            "hash" => [hash, hash, hash],
+            "address" => [
+                "0xdef0000000000000000000000000000000000d",
+                "0xdef0000000000000000000000000000000000d",
+                "0xdef0000000000000000000000000000000000e",
+            ],
+            "full_signature" => ["Approval()", "Approval()", "Transfer()"],
+            "name" => ["Approval", "Approval", "Transfer"],
+            "anonymous" => [false, false, false],
+            "num_indexed_args" => [1_u32, 1_u32, 1_u32],
+        ].unwrap();
+
+        let matched = match_logs_all_candidates(log_df, abi_df).unwrap();
+
+        assert_eq!(matched.height(), 2);
+
+        let ranks = matched.column("candidate_rank").unwrap().u32().unwrap();
+        let confidences = matched.column("match_confidence").unwrap().f64().unwrap();
+        let signatures = matched.column("full_signature").unwrap().str().unwrap();
+
+        let approval_row = (0..2).find(|&i| signatures.get(i) == Some("Approval()")).unwrap();
+        let transfer_row = (0..2).find(|&i| signatures.get(i) == Some("Transfer()")).unwrap();
+
+        assert_eq!(ranks.get(approval_row), Some(1));
+        assert_eq!(ranks.get(transfer_row), Some(2));
+        assert!((confidences.get(approval_row).unwrap() - 2.0 / 3.0).abs() < 1e-9);
+        assert!((confidences.get(transfer_row).unwrap() - 1.0 / 3.0).abs() < 1e-9);
+        assert!((confidences.get(approval_row).unwrap() + confidences.get(transfer_row).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    /// `LogFilter`'s `block_number_range` bound is documented as inclusive on both ends; this
+    /// pins that a log exactly at `start` or `end` is kept while one immediately outside either
+    /// bound is dropped, alongside the `address`/`topic0` `is_in` predicates.
+    #[test]
+    fn log_filter_applies_inclusive_block_range_and_is_in_predicates() {
+        let address_alias = get_config().log_decoder.log_schema.log_alias.address;
+        let topic0_alias = get_config().log_decoder.log_schema.log_alias.topic0;
+        let block_number_alias = get_config().log_decoder.log_schema.log_alias.block_number;
+
+        let address = "0xabc0000000000000000000000000000000000a";
+        let other_address = "0xdef0000000000000000000000000000000000d";
+        let hash = "0x1111111111111111111111111111111111111111111111111111111111111a";
+        let other_hash = "0xffff";
+
+        // Row by row: just below the range, at the start bound, at the end bound, just above the
+        // range, in range but with a topic0 outside the is_in list, and in range but with an
+        // address outside the is_in list.
+        let log_df = df![
+            address_alias.as_str() => [address, address, address, address, address, other_address],
+            topic0_alias.as_str() => [hash, hash, hash, hash, other_hash, hash],
+            block_number_alias.as_str() => [99_i64, 100_i64, 200_i64, 201_i64, 150_i64, 150_i64],
+        ].unwrap();
+
+        let filter = LogFilter {
+            address: Some(vec![address.to_string()]),
+            topic0: Some(vec![hash.to_string()]),
+            block_number_range: Some((100, 200)),
+        };
+
+        let filtered = filter.apply(log_df.lazy()).collect().unwrap();
+        let blocks = filtered.column(block_number_alias.as_str()).unwrap().i64().unwrap();
+
+        // Only the two rows at the inclusive bounds match address, topic0, and the block range.
+        assert_eq!(filtered.height(), 2);
+        assert_eq!(blocks.get(0), Some(100));
+        assert_eq!(blocks.get(1), Some(200));
+    }
+}